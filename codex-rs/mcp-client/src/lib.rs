@@ -0,0 +1,7 @@
+mod mcp_client;
+mod transport;
+
+pub use mcp_client::McpClient;
+pub use transport::HttpTransport;
+pub use transport::StdioTransport;
+pub use transport::Transport;
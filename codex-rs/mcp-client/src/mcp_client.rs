@@ -1,23 +1,40 @@
 //! A minimal async client for the Model Context Protocol (MCP).
 //!
 //! The client is intentionally lightweight – it is only capable of:
-//!   1. Spawning a subprocess that launches a conforming MCP server that
-//!      communicates over stdio.
-//!   2. Sending MCP requests and pairing them with their corresponding
+//!   1. Talking to a conforming MCP server over a pluggable [`Transport`]
+//!      (stdio subprocess or Streamable HTTP/SSE).
+//!   2. Performing the `initialize` handshake required before any other
+//!      request may be sent.
+//!   3. Sending MCP requests and pairing them with their corresponding
 //!      responses.
-//!   3. Offering a convenience helper for the common `tools/list` request.
+//!   4. Offering a convenience helper for the common `tools/list` request.
+//!   5. Sending notifications, subscribing to the server's notifications,
+//!      and answering server-initiated requests via registered handlers.
+//!   6. Timing out or explicitly cancelling in-flight requests.
 //!
 //! The crate hides all JSON‐RPC framing details behind a typed API. Users
 //! interact with the [`ModelContextProtocolRequest`] trait from `mcp-types` to
 //! issue requests and receive strongly-typed results.
 
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use futures::StreamExt;
+use mcp_types::ClientCapabilities;
+use mcp_types::Implementation;
+use mcp_types::InitializeRequest;
+use mcp_types::InitializeRequestParams;
+use mcp_types::InitializeResult;
+use mcp_types::JSONRPCError;
+use mcp_types::JSONRPCErrorError;
 use mcp_types::JSONRPCMessage;
 use mcp_types::JSONRPCNotification;
 use mcp_types::JSONRPCRequest;
@@ -25,15 +42,17 @@ use mcp_types::JSONRPCResponse;
 use mcp_types::ListToolsRequest;
 use mcp_types::ListToolsRequestParams;
 use mcp_types::ListToolsResult;
+use mcp_types::ModelContextProtocolNotification;
 use mcp_types::ModelContextProtocolRequest;
 use mcp_types::RequestId;
+use mcp_types::ServerCapabilities;
+use mcp_types::MCP_SCHEMA_VERSION;
 use mcp_types::JSONRPC_VERSION;
+use reqwest::Url;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use tokio::io::AsyncBufReadExt;
-use tokio::io::AsyncWriteExt;
-use tokio::io::BufReader;
-use tokio::process::Command;
+use serde_json::Value;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::Mutex;
@@ -41,30 +60,118 @@ use tracing::error;
 use tracing::info;
 use tracing::warn;
 
-/// Capacity of the bounded channels used for transporting messages between the
-/// client API and the IO tasks.
-const CHANNEL_CAPACITY: usize = 128;
+use crate::transport::HttpTransport;
+use crate::transport::StdioTransport;
+use crate::transport::Transport;
+
+/// Name we report to servers as part of the `initialize` handshake.
+const CLIENT_NAME: &str = "codex-mcp-client";
+
+/// JSON-RPC reserved error code for "the method does not exist / is not
+/// available", used to answer server requests with no registered handler.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Default ceiling on how long [`McpClient::send_request`] waits for a
+/// response before giving up and cancelling the request server-side.
+/// Overridable per-client via [`McpClient::set_default_timeout`] and
+/// per-call via [`McpClient::send_request_with_timeout`].
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Capacity of the broadcast channel used to fan out inbound notifications to
+/// [`McpClient::subscribe_notifications`] subscribers. Lagging subscribers
+/// miss the oldest buffered notifications rather than block the reader task.
+const NOTIFICATION_CHANNEL_CAPACITY: usize = 128;
+
+/// Internal representation of a pending request sender. `Err` carries a
+/// terminal failure description (e.g. "server terminated" plus recent
+/// stderr) used when the connection goes away before a reply arrives.
+type PendingSender = oneshot::Sender<std::result::Result<JSONRPCMessage, String>>;
+
+/// Map of `request.id -> oneshot::Sender` used to dispatch responses back to
+/// the originating caller.
+type PendingMap = Arc<Mutex<HashMap<i64, PendingSender>>>;
+
+/// Future returned by a registered server-request handler.
+type RequestHandlerFuture =
+    Pin<Box<dyn Future<Output = std::result::Result<Value, JSONRPCErrorError>> + Send>>;
+
+/// Type-erased handler for a server-initiated `JSONRPCRequest`, keyed by
+/// method name in [`McpClient`]'s handler registry. Constructed via
+/// [`McpClient::on_request`], which takes care of (de)serializing the typed
+/// `ModelContextProtocolRequest` params/result.
+type RequestHandler = Arc<dyn Fn(RequestId, Option<Value>) -> RequestHandlerFuture + Send + Sync>;
+
+/// Map of method name -> handler used to answer server-initiated requests.
+type HandlerMap = Arc<Mutex<HashMap<String, RequestHandler>>>;
+
+/// Build the `notifications/cancelled` notification for `id`, per the MCP
+/// spec: sent when we give up on a request (timeout or explicit
+/// cancellation) so the server can stop working on it.
+fn cancelled_notification(id: i64) -> JSONRPCMessage {
+    JSONRPCMessage::Notification(JSONRPCNotification {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        method: "notifications/cancelled".to_string(),
+        params: Some(serde_json::json!({ "requestId": id })),
+    })
+}
+
+/// Handle returned alongside a request issued via
+/// [`McpClient::send_request_cancellable`], letting the caller cancel it
+/// explicitly instead of waiting for a response or timeout.
+pub struct RequestCancelHandle {
+    id: i64,
+    pending: PendingMap,
+    transport: Arc<dyn Transport>,
+}
 
-/// Internal representation of a pending request sender.
-type PendingSender = oneshot::Sender<JSONRPCMessage>;
+impl RequestCancelHandle {
+    /// Cancel the associated request, if it hasn't already completed.
+    ///
+    /// This drops the pending entry (so the in-flight `send_request_*` call
+    /// resolves with an error instead of hanging) and fires
+    /// `notifications/cancelled` so the server can stop working on it.
+    pub async fn cancel(&self) {
+        if let Some(tx) = self.pending.lock().await.remove(&self.id) {
+            let _ = tx.send(Err("request cancelled by caller".to_string()));
+            let _ = self.transport.send(cancelled_notification(self.id)).await;
+        }
+    }
+}
 
 /// A running MCP client instance.
+///
+/// This is transport-agnostic: it only knows how to frame JSON-RPC messages
+/// and dispatch them, via whichever [`Transport`] it was built with. See
+/// [`McpClient::new_stdio_client`] and [`McpClient::new_http_client`] for the
+/// constructors most callers want.
 pub struct McpClient {
-    /// Retain this child process until the client is dropped. The Tokio runtime
-    /// will make a "best effort" to reap the process after it exits, but it is
-    /// not a guarantee. See the `kill_on_drop` documentation for details.
-    #[allow(dead_code)]
-    child: tokio::process::Child,
-
-    /// Channel for sending JSON-RPC messages *to* the background writer task.
-    outgoing_tx: mpsc::Sender<JSONRPCMessage>,
+    /// Where JSON-RPC messages are actually sent/received. Shared (via `Arc`)
+    /// with the reader task and with any [`RequestCancelHandle`]s handed out.
+    transport: Arc<dyn Transport>,
 
     /// Map of `request.id -> oneshot::Sender` used to dispatch responses back
     /// to the originating caller.
-    pending: Arc<Mutex<HashMap<i64, PendingSender>>>,
+    pending: PendingMap,
 
     /// Monotonically increasing counter used to generate request IDs.
     id_counter: AtomicI64,
+
+    /// Capabilities the server advertised in its `InitializeResult`. Set
+    /// during construction, once the handshake has completed.
+    server_capabilities: Mutex<Option<ServerCapabilities>>,
+
+    /// Handlers for server-initiated requests (e.g. `sampling/createMessage`,
+    /// `roots/list`), registered via [`McpClient::on_request`].
+    handlers: HandlerMap,
+
+    /// Default timeout applied by [`McpClient::send_request`], in
+    /// milliseconds. Stored as an atomic so [`McpClient::set_default_timeout`]
+    /// can be called concurrently with in-flight requests.
+    default_timeout_ms: AtomicU64,
+
+    /// Fan-out for inbound `JSONRPCNotification`s, subscribed to via
+    /// [`McpClient::subscribe_notifications`].
+    notification_tx: broadcast::Sender<JSONRPCNotification>,
 }
 
 impl McpClient {
@@ -82,120 +189,359 @@ impl McpClient {
     /// # Ok(()) }
     /// ```
     pub async fn new_stdio_client(args: Vec<String>) -> std::io::Result<Self> {
-        if args.is_empty() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "expected at least one element in `args` - the program to spawn",
-            ));
-        }
+        Self::new_stdio_client_with_stderr_sink(args, None).await
+    }
 
-        let program = &args[0];
-        let mut command = Command::new(program);
-        if args.len() > 1 {
-            command.args(&args[1..]);
-        }
+    /// Same as [`McpClient::new_stdio_client`], but additionally forwards
+    /// every line the server writes to stderr to `stderr_sink` (e.g. to
+    /// surface startup failures in a UI), in addition to the usual
+    /// `tracing` log and the transport's own diagnostics buffer.
+    pub async fn new_stdio_client_with_stderr_sink(
+        args: Vec<String>,
+        stderr_sink: Option<mpsc::Sender<String>>,
+    ) -> std::io::Result<Self> {
+        let transport = StdioTransport::spawn(args, stderr_sink)?;
+        Self::with_transport(Arc::new(transport))
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    }
 
-        command.stdin(std::process::Stdio::piped());
-        command.stdout(std::process::Stdio::piped());
-        command.stderr(std::process::Stdio::null());
-        // As noted in the `kill_on_drop` documentation, the Tokio runtime makes
-        // a "best effort" to reap-after-exit to avoid zombie processes, but it
-        // is not a guarantee.
-        command.kill_on_drop(true);
-        let mut child = command.spawn()?;
-
-        let stdin = child.stdin.take().ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::Other, "failed to capture child stdin")
-        })?;
-        let stdout = child.stdout.take().ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::Other, "failed to capture child stdout")
-        })?;
-
-        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
-        let pending: Arc<Mutex<HashMap<i64, PendingSender>>> = Arc::new(Mutex::new(HashMap::new()));
-
-        // Spawn writer task. It listens on the `outgoing_rx` channel and
-        // writes messages to the child's STDIN.
-        let writer_handle = {
-            let mut stdin = stdin;
-            tokio::spawn(async move {
-                while let Some(msg) = outgoing_rx.recv().await {
-                    match serde_json::to_string(&msg) {
-                        Ok(json) => {
-                            if stdin.write_all(json.as_bytes()).await.is_err() {
-                                error!("failed to write message to child stdin");
-                                break;
-                            }
-                            if stdin.write_all(b"\n").await.is_err() {
-                                error!("failed to write newline to child stdin");
-                                break;
-                            }
-                            if stdin.flush().await.is_err() {
-                                error!("failed to flush child stdin");
-                                break;
-                            }
-                        }
-                        Err(e) => error!("failed to serialize JSONRPCMessage: {e}"),
-                    }
-                }
-            })
-        };
+    /// Connect to a remote MCP server speaking the Streamable HTTP transport
+    /// at `url`, sending `headers` (e.g. `Authorization`) on every request.
+    pub async fn new_http_client(url: Url, headers: Vec<(String, String)>) -> Result<Self> {
+        let transport = HttpTransport::new(url, headers)?;
+        Self::with_transport(Arc::new(transport)).await
+    }
 
-        // Spawn reader task. It reads line-delimited JSON from the child's
-        // STDOUT and dispatches responses to the pending map.
-        let reader_handle = {
+    /// Build a client around an arbitrary [`Transport`] and perform the MCP
+    /// `initialize` handshake over it before returning: send the
+    /// `initialize` request, await the server's `InitializeResult`, and fire
+    /// the `notifications/initialized` notification. The handshake runs to
+    /// completion here, before any `McpClient` exists to hand out, so by
+    /// construction no caller can ever observe one of its own requests
+    /// racing the handshake.
+    pub async fn with_transport(transport: Arc<dyn Transport>) -> Result<Self> {
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let handlers: HandlerMap = Arc::new(Mutex::new(HashMap::new()));
+        let (notification_tx, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+
+        // Spawn the task that consumes inbound messages from the transport
+        // and dispatches them to the pending map / handler registry.
+        {
             let pending = pending.clone();
-            let mut lines = BufReader::new(stdout).lines();
-
+            let handlers = handlers.clone();
+            let transport = transport.clone();
+            let notification_tx = notification_tx.clone();
+            let mut incoming = transport.incoming();
             tokio::spawn(async move {
-                while let Ok(Some(line)) = lines.next_line().await {
-                    match serde_json::from_str::<JSONRPCMessage>(&line) {
-                        Ok(JSONRPCMessage::Response(resp)) => {
+                while let Some(msg) = incoming.next().await {
+                    match msg {
+                        JSONRPCMessage::Response(resp) => {
                             Self::dispatch_response(resp, &pending).await;
                         }
-                        Ok(JSONRPCMessage::Error(err)) => {
+                        JSONRPCMessage::Error(err) => {
                             Self::dispatch_error(err, &pending).await;
                         }
-                        Ok(JSONRPCMessage::Notification(JSONRPCNotification { .. })) => {
-                            // For now we only log server-initiated notifications.
-                            info!("<- notification: {}", line);
+                        JSONRPCMessage::Notification(notification) => {
+                            // Ignore the error: it only means there are
+                            // currently no subscribers listening.
+                            let _ = notification_tx.send(notification);
                         }
-                        Ok(other) => {
-                            // Batch responses and requests are currently not
+                        JSONRPCMessage::Request(req) => {
+                            // Spawn so a slow handler (e.g.
+                            // `sampling/createMessage`, which blocks on an
+                            // LLM call) doesn't stall this reader task –
+                            // otherwise no further responses, notifications,
+                            // or server requests could be dispatched until it
+                            // returns, and a handler that itself calls back
+                            // into `send_request` would deadlock.
+                            let handlers = handlers.clone();
+                            let transport = transport.clone();
+                            tokio::spawn(async move {
+                                Self::dispatch_server_request(req, &handlers, transport.as_ref())
+                                    .await;
+                            });
+                        }
+                        other => {
+                            // Batch requests/responses are currently not
                             // expected from the server – log and ignore.
                             info!("<- unhandled message: {:?}", other);
                         }
-                        Err(e) => {
-                            error!("failed to deserialize JSONRPCMessage: {e}; line = {}", line)
-                        }
                     }
                 }
-            })
-        };
 
-        // We intentionally *detach* the tasks. They will keep running in the
-        // background as long as their respective resources (channels/stdin/
-        // stdout) are alive. Dropping `McpClient` cancels the tasks due to
-        // dropped resources.
-        let _ = (writer_handle, reader_handle);
+                // The stream ended, which for `StdioTransport` means the
+                // server process exited (stdout closed). Don't leave
+                // in-flight `send_request` calls hanging forever — fail them
+                // with whatever diagnostics the transport can offer (e.g.
+                // recent stderr lines).
+                let diagnostics = transport.diagnostics();
+                let reason = if diagnostics.is_empty() {
+                    "server terminated: connection closed".to_string()
+                } else {
+                    format!(
+                        "server terminated: connection closed; last stderr:\n{}",
+                        diagnostics.join("\n")
+                    )
+                };
+                Self::fail_all_pending(&pending, reason).await;
+            });
+        }
+
+        let id_counter = AtomicI64::new(1);
+
+        // Perform the `initialize` handshake up front, talking to the
+        // transport and pending map directly (there is no `McpClient` yet
+        // for `send_request` to be called on, so nothing else can possibly
+        // be in flight).
+        let init_params = InitializeRequestParams {
+            protocol_version: MCP_SCHEMA_VERSION.to_string(),
+            capabilities: ClientCapabilities::default(),
+            client_info: Implementation {
+                name: CLIENT_NAME.to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        };
+        let init_result: InitializeResult = Self::raw_send_request::<InitializeRequest>(
+            transport.as_ref(),
+            &pending,
+            &id_counter,
+            init_params,
+            DEFAULT_REQUEST_TIMEOUT,
+        )
+        .await?;
+
+        let initialized_notification = JSONRPCMessage::Notification(JSONRPCNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: "notifications/initialized".to_string(),
+            params: None,
+        });
+        transport.send(initialized_notification).await?;
 
-        Ok(Self {
-            child,
-            outgoing_tx,
+        let client = Self {
+            transport,
             pending,
-            id_counter: AtomicI64::new(1),
-        })
+            id_counter,
+            server_capabilities: Mutex::new(Some(init_result.capabilities)),
+            handlers,
+            default_timeout_ms: AtomicU64::new(DEFAULT_REQUEST_TIMEOUT.as_millis() as u64),
+            notification_tx,
+        };
+
+        Ok(client)
+    }
+
+    /// Capabilities the server advertised during the `initialize` handshake.
+    /// Callers should check this before relying on optional MCP features.
+    pub async fn server_capabilities(&self) -> Option<ServerCapabilities> {
+        self.server_capabilities.lock().await.clone()
+    }
+
+    /// Send an outgoing MCP notification. Unlike requests, notifications have
+    /// no id and never receive a reply, so this returns as soon as the
+    /// message has been handed to the transport.
+    pub async fn send_notification<N>(&self, params: N::Params) -> Result<()>
+    where
+        N: ModelContextProtocolNotification,
+        N::Params: Serialize,
+    {
+        let params_json = serde_json::to_value(&params)?;
+        let params_field = if params_json.is_null() {
+            None
+        } else {
+            Some(params_json)
+        };
+
+        let notification = JSONRPCMessage::Notification(JSONRPCNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: N::METHOD.to_string(),
+            params: params_field,
+        });
+
+        self.transport.send(notification).await
+    }
+
+    /// Subscribe to inbound notifications pushed by the server, e.g.
+    /// `notifications/tools/list_changed`, `notifications/progress`, and
+    /// `notifications/message`. Each subscriber gets its own receiver and
+    /// sees every notification sent after it subscribes; a lagging
+    /// subscriber misses the oldest buffered ones rather than blocking the
+    /// reader task (see [`broadcast::Receiver`]).
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<JSONRPCNotification> {
+        self.notification_tx.subscribe()
+    }
+
+    /// Register a handler for server-initiated requests of type `R` (e.g.
+    /// `sampling/createMessage`, `roots/list`). Replaces any handler
+    /// previously registered for `R::METHOD`.
+    ///
+    /// The reader task invokes `handler` on a spawned task as soon as a
+    /// matching request arrives, then sends the returned result (or error)
+    /// back to the server as the corresponding `JSONRPCResponse` /
+    /// `JSONRPCError`. Methods with no registered handler are answered with
+    /// `-32601 Method not found`.
+    pub async fn on_request<R, F, Fut>(&self, handler: F)
+    where
+        R: ModelContextProtocolRequest,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize,
+        F: Fn(RequestId, R::Params) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<R::Result, JSONRPCErrorError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let wrapped: RequestHandler = Arc::new(move |id, params_json| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let params: R::Params = serde_json::from_value(params_json.unwrap_or(Value::Null))
+                    .map_err(|e| JSONRPCErrorError {
+                        code: -32602,
+                        message: format!("invalid params for {}: {e}", R::METHOD),
+                        data: None,
+                    })?;
+                let result = handler(id, params).await?;
+                serde_json::to_value(result).map_err(|e| JSONRPCErrorError {
+                    code: -32603,
+                    message: format!("failed to serialize result for {}: {e}", R::METHOD),
+                    data: None,
+                })
+            })
+        });
+        self.handlers
+            .lock()
+            .await
+            .insert(R::METHOD.to_string(), wrapped);
     }
 
-    /// Send an arbitrary MCP request and await the typed result.
+    /// Send an arbitrary MCP request and await the typed result, applying the
+    /// client's default timeout (see [`McpClient::set_default_timeout`]).
     pub async fn send_request<R>(&self, params: R::Params) -> Result<R::Result>
     where
         R: ModelContextProtocolRequest,
         R::Params: Serialize,
         R::Result: DeserializeOwned,
     {
-        // Create a new unique ID.
+        self.send_request_with_timeout::<R>(params, self.default_timeout())
+            .await
+    }
+
+    /// Same as [`McpClient::send_request`], but with an explicit timeout
+    /// instead of the client's default. On expiry, the pending entry is
+    /// dropped and `notifications/cancelled` is sent for the request.
+    pub async fn send_request_with_timeout<R>(
+        &self,
+        params: R::Params,
+        timeout: Duration,
+    ) -> Result<R::Result>
+    where
+        R: ModelContextProtocolRequest,
+        R::Params: Serialize,
+        R::Result: DeserializeOwned,
+    {
+        Self::raw_send_request::<R>(
+            self.transport.as_ref(),
+            &self.pending,
+            &self.id_counter,
+            params,
+            timeout,
+        )
+        .await
+    }
+
+    /// Like [`McpClient::send_request`], but also returns a
+    /// [`RequestCancelHandle`] the caller can use to cancel the request
+    /// explicitly (instead of, or in addition to, the timeout) while it is
+    /// still in flight.
+    ///
+    /// The handle is returned immediately, alongside a [`tokio::task::JoinHandle`]
+    /// for the eventual result, rather than after awaiting the response –
+    /// otherwise the request would already be resolved (or timed out, and
+    /// its pending entry gone) by the time the caller could call
+    /// [`RequestCancelHandle::cancel`], making it a no-op.
+    pub async fn send_request_cancellable<R>(
+        &self,
+        params: R::Params,
+        timeout: Duration,
+    ) -> (
+        RequestCancelHandle,
+        tokio::task::JoinHandle<Result<R::Result>>,
+    )
+    where
+        R: ModelContextProtocolRequest + 'static,
+        R::Params: Serialize + Send + 'static,
+        R::Result: DeserializeOwned + Send + 'static,
+    {
         let id = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        let handle = RequestCancelHandle {
+            id,
+            pending: self.pending.clone(),
+            transport: self.transport.clone(),
+        };
+
+        let transport = self.transport.clone();
+        let pending = self.pending.clone();
+        let result_handle = tokio::spawn(async move {
+            Self::raw_send_request_with_id::<R>(transport.as_ref(), &pending, id, params, timeout)
+                .await
+        });
+
+        (handle, result_handle)
+    }
+
+    /// Override the default timeout applied by [`McpClient::send_request`].
+    /// Takes effect for subsequent calls; in-flight requests keep whatever
+    /// timeout they were issued with.
+    pub fn set_default_timeout(&self, timeout: Duration) {
+        self.default_timeout_ms
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn default_timeout(&self) -> Duration {
+        Duration::from_millis(self.default_timeout_ms.load(Ordering::Relaxed))
+    }
+
+    /// Convenience wrapper around `tools/list`.
+    pub async fn list_tools(
+        &self,
+        params: Option<ListToolsRequestParams>,
+    ) -> Result<ListToolsResult> {
+        self.send_request::<ListToolsRequest>(params).await
+    }
+
+    /// Core request/response plumbing, shared by `send_request_with_timeout`
+    /// and the `initialize` handshake (which runs directly against the
+    /// transport and pending map, since there is no `McpClient` yet to call
+    /// `send_request` on).
+    async fn raw_send_request<R>(
+        transport: &dyn Transport,
+        pending: &PendingMap,
+        id_counter: &AtomicI64,
+        params: R::Params,
+        timeout: Duration,
+    ) -> Result<R::Result>
+    where
+        R: ModelContextProtocolRequest,
+        R::Params: Serialize,
+        R::Result: DeserializeOwned,
+    {
+        let id = id_counter.fetch_add(1, Ordering::SeqCst);
+        Self::raw_send_request_with_id::<R>(transport, pending, id, params, timeout).await
+    }
+
+    /// Same as `raw_send_request`, but with the request id supplied by the
+    /// caller (used by `send_request_cancellable`, which needs the id before
+    /// the request is sent in order to hand out a [`RequestCancelHandle`]).
+    async fn raw_send_request_with_id<R>(
+        transport: &dyn Transport,
+        pending: &PendingMap,
+        id: i64,
+        params: R::Params,
+        timeout: Duration,
+    ) -> Result<R::Result>
+    where
+        R: ModelContextProtocolRequest,
+        R::Params: Serialize,
+        R::Result: DeserializeOwned,
+    {
         let request_id = RequestId::Integer(id);
 
         // Serialize params -> JSON. For many request types `Params` is
@@ -217,26 +563,43 @@ impl McpClient {
         let message = JSONRPCMessage::Request(jsonrpc_request);
 
         // oneshot channel for the response.
-        let (tx, rx) = oneshot::channel();
+        let (tx, mut rx) = oneshot::channel();
 
         // Register in pending map *before* sending the message so a race where
         // the response arrives immediately cannot be lost.
         {
-            let mut guard = self.pending.lock().await;
+            let mut guard = pending.lock().await;
             guard.insert(id, tx);
         }
 
-        // Send to writer task.
-        if self.outgoing_tx.send(message).await.is_err() {
-            return Err(anyhow!(
-                "failed to send message to writer task – channel closed"
-            ));
+        // Hand the message off to the transport.
+        if let Err(e) = transport.send(message).await {
+            pending.lock().await.remove(&id);
+            return Err(e);
         }
 
-        // Await the response.
-        let msg = rx
-            .await
-            .map_err(|_| anyhow!("response channel closed before a reply was received"))?;
+        // Race the response against the timeout. `biased` checks `rx` first
+        // so a response that's already ready wins even if the timer has also
+        // elapsed. If the timer wins, only treat it as a real timeout when we
+        // can still remove the pending entry ourselves — if it's already
+        // gone, the response arrived in the gap and `rx` will resolve
+        // immediately below, cancellation-safely.
+        let msg = tokio::select! {
+            biased;
+            result = &mut rx => {
+                Self::unwrap_pending_result(result)?
+            }
+            _ = tokio::time::sleep(timeout) => {
+                if pending.lock().await.remove(&id).is_some() {
+                    let _ = transport.send(cancelled_notification(id)).await;
+                    return Err(anyhow!(
+                        "request `{}` (id {id}) timed out after {timeout:?}",
+                        R::METHOD
+                    ));
+                }
+                Self::unwrap_pending_result(rx.await)?
+            }
+        };
 
         match msg {
             JSONRPCMessage::Response(JSONRPCResponse { result, .. }) => {
@@ -254,19 +617,21 @@ impl McpClient {
         }
     }
 
-    /// Convenience wrapper around `tools/list`.
-    pub async fn list_tools(
-        &self,
-        params: Option<ListToolsRequestParams>,
-    ) -> Result<ListToolsResult> {
-        self.send_request::<ListToolsRequest>(params).await
+    /// Internal helper: unwrap the `Result<Result<JSONRPCMessage, String>,
+    /// RecvError>` produced by awaiting a pending request's oneshot,
+    /// collapsing both failure modes into the crate's `anyhow::Error`.
+    fn unwrap_pending_result(
+        result: std::result::Result<std::result::Result<JSONRPCMessage, String>, oneshot::error::RecvError>,
+    ) -> Result<JSONRPCMessage> {
+        match result {
+            Ok(Ok(msg)) => Ok(msg),
+            Ok(Err(reason)) => Err(anyhow!(reason)),
+            Err(_) => Err(anyhow!("response channel closed before a reply was received")),
+        }
     }
 
     /// Internal helper: route a JSON-RPC *response* object to the pending map.
-    async fn dispatch_response(
-        resp: JSONRPCResponse,
-        pending: &Arc<Mutex<HashMap<i64, PendingSender>>>,
-    ) {
+    async fn dispatch_response(resp: JSONRPCResponse, pending: &PendingMap) {
         let id = match resp.id {
             RequestId::Integer(i) => i,
             RequestId::String(_) => {
@@ -279,34 +644,73 @@ impl McpClient {
 
         if let Some(tx) = pending.lock().await.remove(&id) {
             // Ignore send errors – the receiver might have been dropped.
-            let _ = tx.send(JSONRPCMessage::Response(resp));
+            let _ = tx.send(Ok(JSONRPCMessage::Response(resp)));
         } else {
             warn!(id, "no pending request found for response");
         }
     }
 
     /// Internal helper: route a JSON-RPC *error* object to the pending map.
-    async fn dispatch_error(
-        err: mcp_types::JSONRPCError,
-        pending: &Arc<Mutex<HashMap<i64, PendingSender>>>,
-    ) {
+    async fn dispatch_error(err: JSONRPCError, pending: &PendingMap) {
         let id = match err.id {
             RequestId::Integer(i) => i,
             RequestId::String(_) => return, // see comment above
         };
 
         if let Some(tx) = pending.lock().await.remove(&id) {
-            let _ = tx.send(JSONRPCMessage::Error(err));
+            let _ = tx.send(Ok(JSONRPCMessage::Error(err)));
+        }
+    }
+
+    /// Internal helper: called once the transport's inbound stream ends
+    /// (e.g. the server process exited, closing stdout). Fails every
+    /// outstanding request instead of letting its oneshot hang forever.
+    async fn fail_all_pending(pending: &PendingMap, reason: String) {
+        let mut guard = pending.lock().await;
+        for (_, tx) in guard.drain() {
+            let _ = tx.send(Err(reason.clone()));
         }
     }
-}
 
-impl Drop for McpClient {
-    fn drop(&mut self) {
-        // Even though we have already tagged this process with
-        // `kill_on_drop(true)` above, this extra check has the benefit of
-        // forcing the process to be reaped immediately if it has already exited
-        // instead of waiting for the Tokio runtime to reap it later.
-        let _ = self.child.try_wait();
+    /// Internal helper: answer a server-initiated request by looking up its
+    /// method in the handler registry and sending the result (or a
+    /// `-32601 Method not found` error) back through the transport.
+    async fn dispatch_server_request(
+        req: JSONRPCRequest,
+        handlers: &HandlerMap,
+        transport: &dyn Transport,
+    ) {
+        let JSONRPCRequest {
+            id, method, params, ..
+        } = req;
+
+        let handler = handlers.lock().await.get(&method).cloned();
+        let response = match handler {
+            Some(handler) => match handler(id.clone(), params).await {
+                Ok(result) => JSONRPCMessage::Response(JSONRPCResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id,
+                    result,
+                }),
+                Err(error) => JSONRPCMessage::Error(JSONRPCError {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id,
+                    error,
+                }),
+            },
+            None => JSONRPCMessage::Error(JSONRPCError {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id,
+                error: JSONRPCErrorError {
+                    code: METHOD_NOT_FOUND,
+                    message: format!("Method not found: {method}"),
+                    data: None,
+                },
+            }),
+        };
+
+        if let Err(e) = transport.send(response).await {
+            error!("failed to send response to server-initiated request `{method}`: {e}");
+        }
     }
 }
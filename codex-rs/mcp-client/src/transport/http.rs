@@ -0,0 +1,217 @@
+//! MCP "Streamable HTTP" transport: requests are POSTed to a base URL and
+//! server-to-client traffic (responses, server-initiated requests, and
+//! notifications) arrives on a Server-Sent Events stream opened against the
+//! same URL.
+
+use anyhow::anyhow;
+use anyhow::Result;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use mcp_types::JSONRPCMessage;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use reqwest::Url;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+use super::Transport;
+
+/// Capacity of the bounded channel used to hand inbound messages from the
+/// SSE-reading task to whoever calls [`Transport::incoming`].
+const INCOMING_CHANNEL_CAPACITY: usize = 128;
+
+/// Transport that speaks MCP over plain HTTP + Server-Sent Events.
+pub struct HttpTransport {
+    client: Client,
+    base_url: Url,
+    /// Kept around (in addition to the clone handed to the SSE-reading
+    /// background task) so `send` can also feed in a response that the
+    /// server returned directly in the POST body instead of over SSE.
+    incoming_tx: mpsc::Sender<JSONRPCMessage>,
+    incoming_rx: tokio::sync::Mutex<Option<mpsc::Receiver<JSONRPCMessage>>>,
+}
+
+impl HttpTransport {
+    /// Connect to a remote MCP server at `base_url`, sending `headers` (e.g.
+    /// `Authorization`) on every request, and open the SSE stream used to
+    /// receive server-to-client traffic.
+    pub fn new(base_url: Url, headers: Vec<(String, String)>) -> Result<Self> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| anyhow!("invalid header name `{name}`: {e}"))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| anyhow!("invalid header value for `{name}`: {e}"))?;
+            header_map.insert(name, value);
+        }
+
+        let client = Client::builder().default_headers(header_map).build()?;
+
+        let (incoming_tx, incoming_rx) =
+            mpsc::channel::<JSONRPCMessage>(INCOMING_CHANNEL_CAPACITY);
+
+        tokio::spawn(Self::read_sse_stream(
+            client.clone(),
+            base_url.clone(),
+            incoming_tx.clone(),
+        ));
+
+        Ok(Self {
+            client,
+            base_url,
+            incoming_tx,
+            incoming_rx: tokio::sync::Mutex::new(Some(incoming_rx)),
+        })
+    }
+
+    /// Open the SSE stream and forward each `data:` event, parsed as a
+    /// [`JSONRPCMessage`], to `incoming_tx`.
+    async fn read_sse_stream(
+        client: Client,
+        base_url: Url,
+        incoming_tx: mpsc::Sender<JSONRPCMessage>,
+    ) {
+        let response = match client
+            .get(base_url.clone())
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                error!("failed to open SSE stream to {base_url}: {e}");
+                return;
+            }
+        };
+
+        let mut byte_stream = response.bytes_stream();
+        let mut buf = String::new();
+        while let Some(chunk) = byte_stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    error!("error reading SSE stream from {base_url}: {e}");
+                    break;
+                }
+            };
+            buf.push_str(&String::from_utf8_lossy(&chunk));
+
+            // SSE events are separated by a blank line; each `data:` line
+            // within an event carries (a fragment of) the JSON payload.
+            while let Some(event_end) = buf.find("\n\n") {
+                let event = buf[..event_end].to_string();
+                buf.drain(..event_end + 2);
+
+                if let Some(msg) = parse_sse_event(&event) {
+                    if incoming_tx.send(msg).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Extract the `data:` payload from a single (already delimited) SSE event
+/// and parse it as a [`JSONRPCMessage`]. An event with no data is discarded;
+/// one that fails to parse is logged and discarded. Shared by the background SSE-reading task and by
+/// `HttpTransport::send`, which must handle a server that replies with
+/// `text/event-stream` directly in the POST response body.
+fn parse_sse_event(event: &str) -> Option<JSONRPCMessage> {
+    let data: String = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if data.is_empty() {
+        return None;
+    }
+
+    match serde_json::from_str::<JSONRPCMessage>(&data) {
+        Ok(msg) => Some(msg),
+        Err(e) => {
+            error!("failed to deserialize SSE event: {e}; data = {data}");
+            None
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, message: JSONRPCMessage) -> Result<()> {
+        let response = self
+            .client
+            .post(self.base_url.clone())
+            .json(&message)
+            .send()
+            .await
+            .map_err(|e| anyhow!("failed to POST message to {}: {e}", self.base_url))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "server responded to POST {} with status {}",
+                self.base_url,
+                response.status()
+            ));
+        }
+
+        // Per the MCP "Streamable HTTP" transport, a server may answer a POST
+        // directly in the response body (as `application/json` or a single
+        // `text/event-stream` event) instead of pushing the reply over the
+        // separately-opened SSE stream. If it did, feed it into the same
+        // channel `incoming()` draws from so it reaches the pending map.
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| anyhow!("failed to read POST response body from {}: {e}", self.base_url))?;
+        if body.is_empty() {
+            return Ok(());
+        }
+
+        if content_type.starts_with("text/event-stream") {
+            let text = String::from_utf8_lossy(&body);
+            for event in text.split("\n\n") {
+                if let Some(msg) = parse_sse_event(event) {
+                    let _ = self.incoming_tx.send(msg).await;
+                }
+            }
+        } else {
+            match serde_json::from_slice::<JSONRPCMessage>(&body) {
+                Ok(msg) => {
+                    let _ = self.incoming_tx.send(msg).await;
+                }
+                Err(e) => error!(
+                    "failed to deserialize POST response body from {}: {e}",
+                    self.base_url
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn incoming(&self) -> BoxStream<'static, JSONRPCMessage> {
+        let rx = self
+            .incoming_rx
+            .try_lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+        match rx {
+            Some(rx) => Box::pin(ReceiverStream::new(rx)),
+            None => {
+                error!("HttpTransport::incoming called more than once");
+                Box::pin(futures::stream::empty())
+            }
+        }
+    }
+}
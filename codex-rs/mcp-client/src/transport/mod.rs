@@ -0,0 +1,49 @@
+//! Pluggable transport layer for [`crate::McpClient`].
+//!
+//! `McpClient` itself only knows how to frame/dispatch JSON-RPC messages; it
+//! does not care whether those messages travel over a child process's stdio
+//! or an HTTP connection. That distinction lives entirely behind the
+//! [`Transport`] trait, with one implementation per wire format:
+//!
+//!   * [`StdioTransport`] spawns a subprocess and speaks line-delimited JSON
+//!     over its stdin/stdout, the original (and still most common) MCP
+//!     transport.
+//!   * [`HttpTransport`] POSTs requests to a remote MCP server and consumes
+//!     server-to-client messages from a Server-Sent Events stream, per the
+//!     MCP "Streamable HTTP" transport.
+
+mod http;
+mod stdio;
+
+pub use http::HttpTransport;
+pub use stdio::StdioTransport;
+
+use anyhow::Result;
+use futures::stream::BoxStream;
+use mcp_types::JSONRPCMessage;
+
+/// A bidirectional channel capable of carrying MCP JSON-RPC traffic.
+///
+/// Implementations are responsible for their own background I/O (spawning a
+/// subprocess, holding an HTTP/SSE connection open, etc.); `send` and
+/// `incoming` are simply the client-facing surface of that machinery.
+#[async_trait::async_trait]
+pub trait Transport: Send + Sync {
+    /// Send a single JSON-RPC message to the peer.
+    async fn send(&self, message: JSONRPCMessage) -> Result<()>;
+
+    /// Take ownership of the stream of messages received from the peer.
+    ///
+    /// Transports back this with a single background reader task, so this
+    /// must be called exactly once (by [`crate::McpClient::with_transport`]);
+    /// calling it a second time returns an empty stream.
+    fn incoming(&self) -> BoxStream<'static, JSONRPCMessage>;
+
+    /// Recent out-of-band diagnostics useful for explaining an unexpected
+    /// disconnect (e.g. the last lines a subprocess wrote to stderr before
+    /// exiting). Empty by default; transports with something to report
+    /// override it.
+    fn diagnostics(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
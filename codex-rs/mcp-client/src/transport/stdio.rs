@@ -0,0 +1,208 @@
+//! The original MCP transport: a child process speaking line-delimited JSON
+//! over its own stdin/stdout.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use futures::stream::BoxStream;
+use mcp_types::JSONRPCMessage;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+use tracing::info;
+
+use super::Transport;
+
+/// Capacity of the bounded channel used to hand inbound messages from the
+/// reader task to whoever calls [`Transport::incoming`].
+const INCOMING_CHANNEL_CAPACITY: usize = 128;
+
+/// Number of trailing stderr lines kept around for [`Transport::diagnostics`]
+/// (e.g. to explain why the server just disconnected).
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Transport that spawns a subprocess and communicates with it over stdio.
+pub struct StdioTransport {
+    /// Retain the child until the transport is dropped. The Tokio runtime
+    /// will make a "best effort" to reap the process after it exits, but it
+    /// is not a guarantee. See the `kill_on_drop` documentation for details.
+    child: Mutex<tokio::process::Child>,
+
+    /// Stdin of the child, guarded so `send` can be called from multiple
+    /// tasks concurrently.
+    stdin: Mutex<tokio::process::ChildStdin>,
+
+    /// Receiving end of the background reader task's channel, handed out
+    /// (once) by `incoming`.
+    incoming_rx: Mutex<Option<mpsc::Receiver<JSONRPCMessage>>>,
+
+    /// Trailing lines the child has written to stderr, most recent last.
+    stderr_tail: Arc<std::sync::Mutex<VecDeque<String>>>,
+}
+
+impl StdioTransport {
+    /// Spawn `args[0]` (with `args[1..]` as arguments) and wire up its stdio.
+    ///
+    /// `args` follows the Unix convention where the first element is the
+    /// executable path and the rest are arguments. `stderr_sink`, if given,
+    /// receives a copy of every line the child writes to stderr (in addition
+    /// to it being logged via `tracing` and kept for
+    /// [`Transport::diagnostics`]).
+    pub fn spawn(
+        args: Vec<String>,
+        stderr_sink: Option<mpsc::Sender<String>>,
+    ) -> std::io::Result<Self> {
+        if args.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "expected at least one element in `args` - the program to spawn",
+            ));
+        }
+
+        let program = &args[0];
+        let mut command = Command::new(program);
+        if args.len() > 1 {
+            command.args(&args[1..]);
+        }
+
+        command.stdin(std::process::Stdio::piped());
+        command.stdout(std::process::Stdio::piped());
+        command.stderr(std::process::Stdio::piped());
+        // As noted in the `kill_on_drop` documentation, the Tokio runtime
+        // makes a "best effort" to reap-after-exit to avoid zombie processes,
+        // but it is not a guarantee.
+        command.kill_on_drop(true);
+        let mut child = command.spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Other, "failed to capture child stdin")
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Other, "failed to capture child stdout")
+        })?;
+        let stderr = child.stderr.take().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Other, "failed to capture child stderr")
+        })?;
+
+        let (incoming_tx, incoming_rx) =
+            mpsc::channel::<JSONRPCMessage>(INCOMING_CHANNEL_CAPACITY);
+        let stderr_tail = Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(
+            STDERR_TAIL_LINES,
+        )));
+
+        // Reader task: reads line-delimited JSON from the child's stdout and
+        // forwards each parsed message to whoever is consuming `incoming()`.
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<JSONRPCMessage>(&line) {
+                    Ok(msg) => {
+                        if incoming_tx.send(msg).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!("failed to deserialize JSONRPCMessage: {e}; line = {}", line)
+                    }
+                }
+            }
+        });
+
+        // Stderr task: most MCP servers put startup errors and crash traces
+        // on stderr, so surface it instead of discarding it.
+        {
+            let stderr_tail = stderr_tail.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    info!(target: "mcp_client::stderr", "{line}");
+
+                    {
+                        let mut tail = stderr_tail.lock().unwrap_or_else(|e| e.into_inner());
+                        if tail.len() == STDERR_TAIL_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line.clone());
+                    }
+
+                    if let Some(sink) = &stderr_sink {
+                        // Ignore send errors – the caller may have dropped
+                        // the receiver while still wanting `diagnostics()`.
+                        let _ = sink.send(line).await;
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            incoming_rx: Mutex::new(Some(incoming_rx)),
+            stderr_tail,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for StdioTransport {
+    async fn send(&self, message: JSONRPCMessage) -> Result<()> {
+        let json = serde_json::to_string(&message)?;
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(json.as_bytes())
+            .await
+            .map_err(|e| anyhow!("failed to write message to child stdin: {e}"))?;
+        stdin
+            .write_all(b"\n")
+            .await
+            .map_err(|e| anyhow!("failed to write newline to child stdin: {e}"))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush child stdin: {e}"))
+    }
+
+    fn incoming(&self) -> BoxStream<'static, JSONRPCMessage> {
+        let rx = self
+            .incoming_rx
+            .try_lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+        match rx {
+            Some(rx) => Box::pin(ReceiverStream::new(rx)),
+            None => {
+                error!("StdioTransport::incoming called more than once");
+                Box::pin(futures::stream::empty())
+            }
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<String> {
+        self.stderr_tail
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        // Even though we have already tagged this process with
+        // `kill_on_drop(true)` above, this extra check has the benefit of
+        // forcing the process to be reaped immediately if it has already
+        // exited instead of waiting for the Tokio runtime to reap it later.
+        if let Ok(mut child) = self.child.try_lock() {
+            let _ = child.try_wait();
+        }
+    }
+}
+